@@ -0,0 +1,29 @@
+use axum::{http::StatusCode, response::IntoResponse, Json};
+use thiserror::Error;
+
+use crate::response::Response;
+
+// Crate-wide error type so handlers can bail out with `?` instead of panicking.
+#[derive(Debug, Error)]
+pub enum AppError {
+    #[error("song not found")]
+    SongNotFound,
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> axum::response::Response {
+        let (status, message) = match &self {
+            AppError::SongNotFound => (StatusCode::NOT_FOUND, self.to_string()),
+            AppError::Database(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
+        };
+
+        let body = match self {
+            AppError::SongNotFound => Response::<()>::Failure(message),
+            AppError::Database(_) => Response::<()>::Fatal(message),
+        };
+
+        (status, Json(body)).into_response()
+    }
+}
@@ -11,12 +11,25 @@ use std::{
 
 use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool, sqlite::SqlitePoolOptions, QueryBuilder};
 
+mod error;
+mod refresh;
+mod response;
+mod song_id;
+
+use error::AppError;
+use refresh::RefreshManager;
+use response::Response;
+use song_id::SongId;
+use std::time::Duration;
+
 const DB_URL: &str = "sqlite://songs.db";
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+const SUBSONIC_BASE_URL: &str = "http://localhost:4040";
 
 // Define the Song struct with serialization and deserialization
 #[derive(sqlx::FromRow, Debug, Serialize, Deserialize, Clone)]
 struct Song {
-    id: i32,
+    id: SongId,
     title: String,
     artist: String,
     genre: String,
@@ -30,6 +43,26 @@ struct NewSong {
     title: String,
     artist: String,
     genre: String,
+    user: String,
+}
+
+// A user that has contributed songs to the catalog
+#[derive(sqlx::FromRow, Debug, Serialize, Deserialize, Clone)]
+struct User {
+    id: i32,
+    name: String,
+}
+
+// A user and the songs they've contributed, as returned by `/status`
+#[derive(Debug, Serialize)]
+struct UserSongs {
+    name: String,
+    songs: Vec<Song>,
+}
+
+#[derive(Debug, Serialize)]
+struct StatusResponse {
+    users: Vec<UserSongs>,
 }
 
 // These are the valid params that can be passed to search from
@@ -41,6 +74,14 @@ struct QueryParams {
     genre: Option<String>,
 }
 
+// The valid params for the `/songs/top` leaderboard
+#[derive(Debug, Deserialize)]
+struct TopSongsParams {
+    genre: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
 // State to be shared across the requesting threads
 // State should be sharing context of a database to read/write
 #[derive(Clone)]
@@ -72,21 +113,14 @@ async fn main() {
         .unwrap();
 
 
-    sqlx::query("
-        CREATE TABLE IF NOT EXISTS songs (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            title_lowercase  VARCHAR(250) NOT NULL,
-            genre_lowercase  VARCHAR(250) NOT NULL,
-            artist_lowercase VARCHAR(250) NOT NULL,
-            title VARCHAR(250) NOT NULL,
-            genre VARCHAR(250) NOT NULL,
-            artist VARCHAR(250) NOT NULL,
-            play_count INTEGER DEFAULT 0
-        );")
-        .execute(&db)
+    sqlx::migrate!("./migrations")
+        .run(&db)
         .await
         .unwrap();
 
+    let refresh_manager = RefreshManager::new(db.clone(), SUBSONIC_BASE_URL.to_string(), REFRESH_INTERVAL);
+    tokio::spawn(refresh_manager.run());
+
     let state = AppState { db, visit_count };
 
     // Define the address
@@ -99,6 +133,8 @@ async fn main() {
         .route("/songs/new", post(add_new_song))
         .route("/songs/play/:id", get(play_song))
         .route("/songs/search", get(search_song))
+        .route("/songs/top", get(top_songs))
+        .route("/status", get(get_status))
         .with_state(state);
 
     // Print the message only after the server successfully binds
@@ -122,9 +158,10 @@ async fn handle_count(State(state): State<AppState>) -> String {
 async fn add_new_song(
     State(state): State<AppState>,
     Json(payload): Json<NewSong>,
-) -> Json<Song> {
-    // Insert the new song into the database
+) -> Result<Json<Response<Song>>, AppError> {
+    let mut tx = state.db.begin().await?;
 
+    // Insert the new song into the database
     let result = sqlx::query("
         INSERT INTO songs (title_lowercase, artist_lowercase, genre_lowercase, title, artist, genre, play_count)
         VALUES (?, ?, ?, ?, ?, ?, 0)
@@ -135,21 +172,69 @@ async fn add_new_song(
     .bind(&payload.title)
     .bind(&payload.artist)
     .bind(&payload.genre)
-    .execute(&state.db)
-    .await
-    .unwrap();
+    .execute(&mut *tx)
+    .await?;
 
     let song_id = result.last_insert_rowid() as i32;
+    let user_id = find_or_create_user(&mut tx, &payload.user).await?;
+
+    sqlx::query("INSERT INTO song_contributors (song_id, user_id) VALUES (?, ?)")
+        .bind(song_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(Json(Response::Success(Song {
+        id: SongId(song_id),
+        title: payload.title,
+        artist: payload.artist,
+        genre: payload.genre,
+        play_count: 0,
+    })))
+}
 
-    Json(
-        Song {
-            id: song_id,
-            title: payload.title,
-            artist: payload.artist,
-            genre: payload.genre,
-            play_count: 0,
-        }
-    )
+// Looks up a user by name, creating them if this is their first contribution.
+// The insert-then-select (rather than select-then-insert) avoids a TOCTOU
+// race against the `users.name` unique constraint under concurrent requests.
+async fn find_or_create_user(tx: &mut sqlx::Transaction<'_, Sqlite>, name: &str) -> Result<i32, AppError> {
+    sqlx::query("INSERT INTO users (name) VALUES (?) ON CONFLICT(name) DO NOTHING")
+        .bind(name)
+        .execute(&mut *tx)
+        .await?;
+
+    let user: User = sqlx::query_as("SELECT id, name FROM users WHERE name = ?")
+        .bind(name)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    Ok(user.id)
+}
+
+// Handler for the `/status` endpoint: groups the catalog by contributing user
+async fn get_status(State(state): State<AppState>) -> Result<Json<Response<StatusResponse>>, AppError> {
+    let db = &state.db;
+
+    let users: Vec<User> = sqlx::query_as("SELECT id, name FROM users")
+        .fetch_all(db)
+        .await?;
+
+    let mut user_songs = Vec::with_capacity(users.len());
+    for user in users {
+        let songs: Vec<Song> = sqlx::query_as("
+            SELECT songs.* FROM songs
+            JOIN song_contributors ON song_contributors.song_id = songs.id
+            WHERE song_contributors.user_id = ?
+        ")
+        .bind(user.id)
+        .fetch_all(db)
+        .await?;
+
+        user_songs.push(UserSongs { name: user.name, songs });
+    }
+
+    Ok(Json(Response::Success(StatusResponse { users: user_songs })))
 }
 
 
@@ -157,7 +242,7 @@ async fn add_new_song(
 async fn search_song(
     State(state): State<AppState>,
     Query(params): Query<QueryParams>,
-) -> Json<Vec<Song>> {
+) -> Result<Json<Response<Vec<Song>>>, AppError> {
     let db = &state.db;
 
     // Start building the query
@@ -178,18 +263,47 @@ async fn search_song(
     let songs: Vec<Song> = query_builder
         .build_query_as::<Song>() // Map rows to the Song struct
         .fetch_all(db)
-        .await
-        .unwrap_or_else(|_| Vec::new()); // Handle errors gracefully by returning an empty list
+        .await?;
 
     // Return the results as a JSON response
-    Json(songs)
+    Ok(Json(Response::Success(songs)))
+}
+
+const DEFAULT_TOP_SONGS_LIMIT: i64 = 20;
+
+// Handler for the `/songs/top` leaderboard: most-played songs, optionally
+// filtered by genre, with limit/offset pagination
+async fn top_songs(
+    State(state): State<AppState>,
+    Query(params): Query<TopSongsParams>,
+) -> Result<Json<Vec<Song>>, AppError> {
+    let db = &state.db;
+
+    let mut query_builder = QueryBuilder::<Sqlite>::new("SELECT * FROM songs WHERE 1=1");
+
+    if let Some(genre) = &params.genre {
+        query_builder.push(" AND genre_lowercase LIKE ").push_bind(format!("%{}%", genre.to_lowercase()));
+    }
+
+    query_builder.push(" ORDER BY play_count DESC");
+    query_builder.push(" LIMIT ").push_bind(params.limit.unwrap_or(DEFAULT_TOP_SONGS_LIMIT));
+    query_builder.push(" OFFSET ").push_bind(params.offset.unwrap_or(0));
+
+    let songs: Vec<Song> = query_builder
+        .build_query_as::<Song>()
+        .fetch_all(db)
+        .await?;
+
+    // Bare Vec<Song> to match the request's spec, rather than the Response<T>
+    // envelope the other handlers use.
+    Ok(Json(songs))
 }
 
 // // Handler to play a song by ID
 async fn play_song(
     State(state): State<AppState>,
-    Path(id): Path<usize>,
-) -> Json<serde_json::Value> {
+    Path(id): Path<SongId>,
+) -> Result<Json<Response<Song>>, AppError> {
     let db = &state.db;
 
     // Increment the play_count for the song with the given ID
@@ -200,35 +314,27 @@ async fn play_song(
         WHERE id = ?
         "
     )
-    .bind(id as i32) // Binding the ID
+    .bind(id.0) // Binding the ID
     .execute(db) // Execute the query
-    .await
-    .unwrap()
+    .await?
     .rows_affected();
 
     // Check if the song exists
     if rows_affected == 0 {
-        return Json(serde_json::json!({"error": "Song not found"}));
+        return Err(AppError::SongNotFound);
     }
 
     // Fetch the updated song details
-    let song: (i32, String, String, String, i32) = sqlx::query_as("
+    let song: Song = sqlx::query_as("
         SELECT id, title, artist, genre, play_count
         FROM songs
         WHERE id = ?
     "
     )
-    .bind(id as i32)
+    .bind(id.0)
     .fetch_one(db) // Fetch the single row
-    .await
-    .unwrap();
+    .await?;
 
     // Return the updated song as a JSON response
-    Json(serde_json::json!(Song {
-        id: song.0,
-        title: song.1,
-        genre: song.2,
-        artist: song.3,
-        play_count: song.4
-    }))
+    Ok(Json(Response::Success(song)))
 }
@@ -0,0 +1,93 @@
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+// Periodically syncs play counts from an upstream Subsonic server.
+pub struct RefreshManager {
+    db: SqlitePool,
+    client: reqwest::Client,
+    base_url: String,
+    interval: Duration,
+}
+
+// A single now-playing/scrobble entry returned by Subsonic's `getNowPlaying`
+#[derive(Debug, Deserialize)]
+struct NowPlayingEntry {
+    title: String,
+    artist: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct NowPlaying {
+    entry: Vec<NowPlayingEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NowPlayingResponse {
+    #[serde(rename = "nowPlaying")]
+    now_playing: NowPlaying,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubsonicResponse {
+    #[serde(rename = "subsonic-response")]
+    subsonic_response: NowPlayingResponse,
+}
+
+impl RefreshManager {
+    pub fn new(db: SqlitePool, base_url: String, interval: Duration) -> Self {
+        Self {
+            db,
+            client: reqwest::Client::new(),
+            base_url,
+            interval,
+        }
+    }
+
+    // Runs refresh_all on self.interval until the process exits.
+    pub async fn run(self) {
+        let mut ticker = tokio::time::interval(self.interval);
+        loop {
+            ticker.tick().await;
+            if let Err(error) = self.refresh_all().await {
+                eprintln!("refresh_all failed: {}", error);
+            }
+        }
+    }
+
+    async fn refresh_all(&self) -> Result<(), sqlx::Error> {
+        let now_playing = match self.fetch_now_playing().await {
+            Ok(entries) => entries,
+            Err(error) => {
+                eprintln!("failed to fetch now-playing from upstream: {}", error);
+                return Ok(());
+            }
+        };
+
+        for entry in now_playing {
+            self.refresh_song(&entry.title, &entry.artist).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn fetch_now_playing(&self) -> Result<Vec<NowPlayingEntry>, reqwest::Error> {
+        let url = format!("{}/rest/getNowPlaying.view", self.base_url);
+        let response = self.client.get(&url).send().await?.json::<SubsonicResponse>().await?;
+        Ok(response.subsonic_response.now_playing.entry)
+    }
+
+    async fn refresh_song(&self, title: &str, artist: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("
+            UPDATE songs
+            SET play_count = play_count + 1
+            WHERE title_lowercase = ? AND artist_lowercase = ?
+        ")
+        .bind(title.to_lowercase())
+        .bind(artist.to_lowercase())
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+}
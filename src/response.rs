@@ -0,0 +1,10 @@
+use serde::Serialize;
+
+// Tagged Success/Failure/Fatal envelope returned by handlers.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "content")]
+pub enum Response<T> {
+    Success(T),
+    Failure(String),
+    Fatal(String),
+}
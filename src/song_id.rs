@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+const ALPHABET: &[u8; 62] =
+    b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+// Opaque, reversible stand-in for a `songs.id` row value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SongId(pub i32);
+
+impl SongId {
+    pub fn encode(id: i32) -> String {
+        let offset = checksum(id);
+        let alphabet = shuffled_alphabet(offset);
+        let mut digits = to_base(id as u64, &alphabet);
+        // The prefix character comes from the *unshuffled* alphabet so it
+        // encodes `offset` directly; decode can then look it up with a
+        // single `position()` instead of guessing at the shuffle used.
+        digits.insert(0, ALPHABET[offset as usize] as char);
+        digits
+    }
+
+    pub fn decode(encoded: &str) -> Option<i32> {
+        let bytes = encoded.as_bytes();
+        if bytes.is_empty() {
+            return None;
+        }
+
+        let offset = ALPHABET.iter().position(|&c| c == bytes[0])? as u32;
+        let alphabet = shuffled_alphabet(offset);
+        let value = from_base(&encoded[1..], &alphabet)?;
+
+        if checksum(value as i32) != offset {
+            return None;
+        }
+
+        Some(value as i32)
+    }
+}
+
+impl fmt::Display for SongId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", SongId::encode(self.0))
+    }
+}
+
+impl FromStr for SongId {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SongId::decode(s).map(SongId).ok_or("malformed song id")
+    }
+}
+
+impl Serialize for SongId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for SongId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        SongId::decode(&s)
+            .map(SongId)
+            .ok_or_else(|| serde::de::Error::custom("malformed song id"))
+    }
+}
+
+fn checksum(id: i32) -> u32 {
+    (id as u32) % ALPHABET.len() as u32
+}
+
+fn shuffled_alphabet(offset: u32) -> Vec<u8> {
+    let offset = offset as usize % ALPHABET.len();
+    let mut shuffled: Vec<u8> = ALPHABET.to_vec();
+    shuffled.rotate_left(offset);
+    shuffled
+}
+
+fn to_base(mut value: u64, alphabet: &[u8]) -> String {
+    let base = alphabet.len() as u64;
+    if value == 0 {
+        return (alphabet[0] as char).to_string();
+    }
+
+    let mut digits = Vec::new();
+    while value > 0 {
+        digits.push(alphabet[(value % base) as usize]);
+        value /= base;
+    }
+    digits.reverse();
+    String::from_utf8(digits).expect("alphabet is ASCII")
+}
+
+// Delegate to `i32`'s sqlx encoding so `#[derive(sqlx::FromRow)]` keeps working.
+impl sqlx::Type<sqlx::Sqlite> for SongId {
+    fn type_info() -> sqlx::sqlite::SqliteTypeInfo {
+        <i32 as sqlx::Type<sqlx::Sqlite>>::type_info()
+    }
+}
+
+impl<'r> sqlx::Decode<'r, sqlx::Sqlite> for SongId {
+    fn decode(value: sqlx::sqlite::SqliteValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let id = <i32 as sqlx::Decode<sqlx::Sqlite>>::decode(value)?;
+        Ok(SongId(id))
+    }
+}
+
+impl<'q> sqlx::Encode<'q, sqlx::Sqlite> for SongId {
+    fn encode_by_ref(&self, buf: &mut Vec<sqlx::sqlite::SqliteArgumentValue<'q>>) -> sqlx::encode::IsNull {
+        <i32 as sqlx::Encode<sqlx::Sqlite>>::encode_by_ref(&self.0, buf)
+    }
+}
+
+fn from_base(s: &str, alphabet: &[u8]) -> Option<u64> {
+    if s.is_empty() {
+        return None;
+    }
+
+    let base = alphabet.len() as u64;
+    let mut value: u64 = 0;
+    for byte in s.bytes() {
+        let digit = alphabet.iter().position(|&c| c == byte)? as u64;
+        value = value.checked_mul(base)?.checked_add(digit)?;
+    }
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_round_trips() {
+        for id in 0..5000 {
+            let encoded = SongId::encode(id);
+            assert_eq!(SongId::decode(&encoded), Some(id), "round trip failed for {}", id);
+        }
+    }
+
+    #[test]
+    fn decode_rejects_garbage() {
+        assert_eq!(SongId::decode(""), None);
+        assert_eq!(SongId::decode("!!!"), None);
+    }
+}